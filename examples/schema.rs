@@ -4,9 +4,12 @@ use std::fs::create_dir_all;
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
 
 use marketplace::msg::{
-    AllAuctionsResponse, AuctionResponse, BidResponse, CharityResponse, ConfigResponse, ExecuteMsg,
-    HistoryBidResponse, HistoryResponse, InstantiateMsg, MigrateMsg, QueryMsg, RoyaltyResponse,
-    StateResponse,
+    AllAuctionsResponse, AuctionResponse, BidResponse, CharitiesResponse, CharityResponse,
+    ClaimableResponse, ConfigResponse, DonationReceipt, EmissionResponse, EscrowedNftResponse,
+    ExecuteMsg, FeeForResponse, HistoryBidResponse, HistoryResponse, InstantiateMsg, MigrateMsg,
+    NextMinBidResponse, QueryMsg, ReferralResponse, RentalResponse, RoyaltyOverridesResponse,
+    RoyaltyResponse, StakerResponse, StakingStateResponse, StateResponse, StatsResponse,
+    UserOfResponse, VestingResponse,
 };
 use marketplace::state::{Config, State};
 
@@ -25,10 +28,25 @@ fn main() {
     export_schema(&schema_for!(AllAuctionsResponse), &out_dir);
     export_schema(&schema_for!(BidResponse), &out_dir);
     export_schema(&schema_for!(CharityResponse), &out_dir);
+    export_schema(&schema_for!(CharitiesResponse), &out_dir);
+    export_schema(&schema_for!(DonationReceipt), &out_dir);
+    export_schema(&schema_for!(FeeForResponse), &out_dir);
+    export_schema(&schema_for!(StakerResponse), &out_dir);
+    export_schema(&schema_for!(StakingStateResponse), &out_dir);
+    export_schema(&schema_for!(EmissionResponse), &out_dir);
+    export_schema(&schema_for!(VestingResponse), &out_dir);
+    export_schema(&schema_for!(ReferralResponse), &out_dir);
+    export_schema(&schema_for!(StatsResponse), &out_dir);
     export_schema(&schema_for!(ConfigResponse), &out_dir);
     export_schema(&schema_for!(StateResponse), &out_dir);
     export_schema(&schema_for!(HistoryBidResponse), &out_dir);
     export_schema(&schema_for!(HistoryResponse), &out_dir);
     export_schema(&schema_for!(RoyaltyResponse), &out_dir);
+    export_schema(&schema_for!(RoyaltyOverridesResponse), &out_dir);
+    export_schema(&schema_for!(NextMinBidResponse), &out_dir);
+    export_schema(&schema_for!(RentalResponse), &out_dir);
+    export_schema(&schema_for!(UserOfResponse), &out_dir);
+    export_schema(&schema_for!(ClaimableResponse), &out_dir);
+    export_schema(&schema_for!(EscrowedNftResponse), &out_dir);
     export_schema(&schema_for!(MigrateMsg), &out_dir);
 }
@@ -1,4 +1,4 @@
-use cosmwasm_std::{StdError, Uint128};
+use cosmwasm_std::{Decimal, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug, PartialEq)]
@@ -56,6 +56,54 @@ pub enum ContractError {
 
     #[error("You need to pay fees for canceling this auction please send {0} {1}")]
     CancelAuctionFee(String, String),
+
+    #[error("Only holders of a {0} NFT can bid on this auction")]
+    CollectionHolderRestriction(String),
+
+    #[error("Bid increments need to be sorted by ascending price")]
+    IncrementsNotSorted {},
+
+    #[error("Fee tiers need to be sorted by ascending SITY amount")]
+    FeeTiersNotSorted {},
+
+    #[error("Proxy bid max is {0}, your total sent with this current amount is {1}")]
+    ProxyBidEscrow(Uint128, Uint128),
+
+    #[error("Rental duration need to be between 10 min and 6 months")]
+    RentalDurationLimit {},
+
+    #[error("Rental price is {0}, you sent {1}")]
+    RentalPrice(Uint128, Uint128),
+
+    #[error("NFT is rented until {0}")]
+    RentalActive(u64),
+
+    #[error("Nothing to claim")]
+    NothingToClaim {},
+
+    #[error("Not enough SITY staked")]
+    InsufficientStake {},
+
+    #[error("Unbonding period not over")]
+    UnbondingNotReady {},
+
+    #[error("You can't refer yourself")]
+    SelfReferral {},
+
+    #[error("Wash trading guard windows can't exceed {0} seconds and one need to be set")]
+    WashTradingWindow(u64),
+
+    #[error("Emission epoch duration can't be zero")]
+    EmissionEpochDuration {},
+
+    #[error("Charity {0} is not a verified charity at this address")]
+    CharityNotVerified(u64),
+
+    #[error("Royalty split shares need to be positive and sum to the royalty fee {0}")]
+    RoyaltySplitShares(Decimal),
+
+    #[error("Minter of {0} can't be found, use a royalty override instead")]
+    UnknownMinter(String),
     // Add any other custom errors you like here.
     // Look at https://docs.rs/thiserror/1.0.21/thiserror/ for details.
 }
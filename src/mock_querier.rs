@@ -1,19 +1,14 @@
-use crate::msg::QueryTalisMsg;
+use crate::msg::{QueryCw2981Msg, RoyaltiesInfoResponse};
 use crate::state::TalisInfo;
-use cosmwasm_bignumber::{Decimal256, Uint256};
 use cosmwasm_std::testing::{MockApi, MockQuerier, MockStorage, MOCK_CONTRACT_ADDR};
 use cosmwasm_std::{
-    from_slice, to_binary, Addr, Api, BankQuery, Binary, Coin, ContractResult, Decimal, OwnedDeps,
-    Querier, QuerierResult, QueryRequest, Response, StdError, StdResult, SystemError, SystemResult,
-    Uint128, WasmQuery,
-};
-use cw20::BalanceResponse;
-use serde::Serialize;
-use std::str::FromStr;
-use terra_cosmwasm::{
-    ExchangeRateItem, ExchangeRatesResponse, TaxCapResponse, TaxRateResponse, TerraQuery,
-    TerraQueryWrapper, TerraRoute,
+    from_binary, from_slice, to_binary, Coin, ContractResult, Decimal, OwnedDeps, Querier,
+    QuerierResult, QueryRequest, SystemError, SystemResult, Uint128, WasmQuery,
 };
+use cw20::{BalanceResponse, Cw20QueryMsg};
+use cw721::{Cw721QueryMsg, OwnerOfResponse, TokensResponse};
+use std::collections::HashMap;
+use terra_cosmwasm::{TaxCapResponse, TaxRateResponse, TerraQuery, TerraQueryWrapper};
 //pub const MOCK_HUB_CONTRACT_ADDR: &str = "hub";
 //pub const MOCK_CW20_CONTRACT_ADDR: &str = "lottery";
 //pub const MOCK_REWARD_CONTRACT_ADDR: &str = "reward";
@@ -33,6 +28,97 @@ pub fn mock_dependencies_custom(
 
 pub struct WasmMockQuerier {
     base: MockQuerier<TerraQueryWrapper>,
+    cw721_querier: Cw721Querier,
+    cw2981_querier: Cw2981Querier,
+    cw20_querier: Cw20Querier,
+    tax_querier: Option<TaxQuerier>,
+}
+
+#[derive(Clone)]
+pub struct TaxQuerier {
+    rate: Decimal,
+    cap: Uint128,
+}
+
+impl Default for TaxQuerier {
+    fn default() -> Self {
+        TaxQuerier {
+            rate: Decimal::percent(1),
+            cap: Uint128::from(1000000u128),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Cw721Querier {
+    // collection address -> token id -> owner
+    owners: HashMap<String, HashMap<String, String>>,
+}
+
+impl Cw721Querier {
+    pub fn new(owners: &[(&str, &[(&str, &str)])]) -> Self {
+        Cw721Querier {
+            owners: owners_to_map(owners),
+        }
+    }
+}
+
+pub(crate) fn owners_to_map(
+    owners: &[(&str, &[(&str, &str)])],
+) -> HashMap<String, HashMap<String, String>> {
+    let mut owners_map: HashMap<String, HashMap<String, String>> = HashMap::new();
+    for (contract_addr, tokens) in owners.iter() {
+        let mut contract_owners_map: HashMap<String, String> = HashMap::new();
+        for (token_id, owner) in tokens.iter() {
+            contract_owners_map.insert(token_id.to_string(), owner.to_string());
+        }
+
+        owners_map.insert(contract_addr.to_string(), contract_owners_map);
+    }
+    owners_map
+}
+
+#[derive(Clone, Default)]
+pub struct Cw20Querier {
+    // token address -> holder -> balance
+    balances: HashMap<String, HashMap<String, Uint128>>,
+}
+
+impl Cw20Querier {
+    pub fn new(balances: &[(&str, &[(&str, Uint128)])]) -> Self {
+        let mut balances_map: HashMap<String, HashMap<String, Uint128>> = HashMap::new();
+        for (contract_addr, holders) in balances.iter() {
+            let mut contract_balances_map: HashMap<String, Uint128> = HashMap::new();
+            for (holder, balance) in holders.iter() {
+                contract_balances_map.insert(holder.to_string(), *balance);
+            }
+            balances_map.insert(contract_addr.to_string(), contract_balances_map);
+        }
+        Cw20Querier {
+            balances: balances_map,
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct Cw2981Querier {
+    // collection address -> (royalty recipient, royalty percentage)
+    royalties: HashMap<String, (String, Decimal)>,
+}
+
+impl Cw2981Querier {
+    pub fn new(royalties: &[(&str, &str, Decimal)]) -> Self {
+        let mut royalties_map: HashMap<String, (String, Decimal)> = HashMap::new();
+        for (contract_addr, recipient, percentage) in royalties.iter() {
+            royalties_map.insert(
+                contract_addr.to_string(),
+                (recipient.to_string(), *percentage),
+            );
+        }
+        Cw2981Querier {
+            royalties: royalties_map,
+        }
+    }
 }
 
 impl Querier for WasmMockQuerier {
@@ -54,6 +140,33 @@ impl WasmMockQuerier {
     pub fn handle_query(&self, request: &QueryRequest<TerraQueryWrapper>) -> QuerierResult {
         match &request {
             QueryRequest::Wasm(WasmQuery::Smart { contract_addr, msg }) => {
+                if let Ok(QueryCw2981Msg::RoyaltyInfo { sale_price, .. }) = from_binary(msg) {
+                    return match self.cw2981_querier.royalties.get(contract_addr) {
+                        None => SystemResult::Ok(ContractResult::Err(
+                            "cw2981 not implemented".to_string(),
+                        )),
+                        Some((recipient, percentage)) => {
+                            let res = RoyaltiesInfoResponse {
+                                address: recipient.to_string(),
+                                royalty_amount: sale_price * *percentage,
+                            };
+                            SystemResult::Ok(ContractResult::from(to_binary(&res)))
+                        }
+                    };
+                }
+                if let Some(balances) = self.cw20_querier.balances.get(contract_addr) {
+                    if let Ok(Cw20QueryMsg::Balance { address }) = from_binary(msg) {
+                        let res = BalanceResponse {
+                            balance: balances.get(&address).cloned().unwrap_or_default(),
+                        };
+                        return SystemResult::Ok(ContractResult::from(to_binary(&res)));
+                    }
+                }
+                if let Some(tokens) = self.cw721_querier.owners.get(contract_addr) {
+                    if let Ok(cw721_msg) = from_binary::<Cw721QueryMsg>(msg) {
+                        return self.handle_cw721_query(tokens, cw721_msg);
+                    }
+                }
                 if contract_addr == &"market".to_string() {
                     println!("{:?}", request);
                     // CW-721 standard
@@ -69,29 +182,115 @@ impl WasmMockQuerier {
                     //let msg_minter = cw20::BalanceResponse{ balance: Uint128::from(100_u128) };
                     return SystemResult::Ok(ContractResult::from(to_binary(&msg_minter)));
                 }
-                panic!("DO NOT ENTER HERE")
+                SystemResult::Err(SystemError::NoSuchContract {
+                    addr: contract_addr.to_string(),
+                })
             }
-            QueryRequest::Custom(TerraQueryWrapper { route, query_data }) => match query_data {
-                TerraQuery::TaxRate {} => {
-                    let res = TaxRateResponse {
-                        rate: Decimal::percent(1),
-                    };
-                    SystemResult::Ok(ContractResult::from(to_binary(&res)))
+            QueryRequest::Custom(TerraQueryWrapper {
+                route: _,
+                query_data,
+            }) => {
+                // Chains without Terra queries reject them
+                let tax_querier = match &self.tax_querier {
+                    None => {
+                        return SystemResult::Err(SystemError::UnsupportedRequest {
+                            kind: "terra".to_string(),
+                        })
+                    }
+                    Some(tax_querier) => tax_querier,
+                };
+                match query_data {
+                    TerraQuery::TaxRate {} => {
+                        let res = TaxRateResponse {
+                            rate: tax_querier.rate,
+                        };
+                        SystemResult::Ok(ContractResult::from(to_binary(&res)))
+                    }
+                    TerraQuery::TaxCap { denom: _ } => {
+                        let res = TaxCapResponse {
+                            cap: tax_querier.cap,
+                        };
+                        SystemResult::Ok(ContractResult::from(to_binary(&res)))
+                    }
+                    _ => panic!("DO NOT ENTER HERE"),
+                }
+            }
+            _ => self.base.handle_query(request),
+        }
+    }
+}
+
+impl WasmMockQuerier {
+    fn handle_cw721_query(
+        &self,
+        tokens: &HashMap<String, String>,
+        msg: Cw721QueryMsg,
+    ) -> QuerierResult {
+        match msg {
+            Cw721QueryMsg::OwnerOf { token_id, .. } => match tokens.get(&token_id) {
+                None => {
+                    SystemResult::Ok(ContractResult::Err(format!("Token {} not found", token_id)))
                 }
-                TerraQuery::TaxCap { denom: _ } => {
-                    let cap = Uint128::from(1000000u128);
-                    let res = TaxCapResponse { cap };
+                Some(owner) => {
+                    let res = OwnerOfResponse {
+                        owner: owner.to_string(),
+                        approvals: vec![],
+                    };
                     SystemResult::Ok(ContractResult::from(to_binary(&res)))
                 }
-                _ => panic!("DO NOT ENTER HERE"),
             },
-            _ => self.base.handle_query(request),
+            Cw721QueryMsg::Tokens {
+                owner,
+                start_after,
+                limit,
+            } => {
+                let mut owned = tokens
+                    .iter()
+                    .filter(|(_, token_owner)| **token_owner == owner)
+                    .map(|(token_id, _)| token_id.to_string())
+                    .filter(|token_id| match &start_after {
+                        None => true,
+                        Some(start) => token_id > start,
+                    })
+                    .collect::<Vec<String>>();
+                owned.sort();
+                owned.truncate(limit.unwrap_or(10) as usize);
+                let res = TokensResponse { tokens: owned };
+                SystemResult::Ok(ContractResult::from(to_binary(&res)))
+            }
+            _ => panic!("DO NOT ENTER HERE"),
         }
     }
 }
 
 impl WasmMockQuerier {
     pub fn new(base: MockQuerier<TerraQueryWrapper>) -> Self {
-        WasmMockQuerier { base }
+        WasmMockQuerier {
+            base,
+            cw721_querier: Cw721Querier::default(),
+            cw2981_querier: Cw2981Querier::default(),
+            cw20_querier: Cw20Querier::default(),
+            tax_querier: Some(TaxQuerier::default()),
+        }
+    }
+
+    // configure the Terra tax, None to mock a chain without Terra queries
+    pub fn with_tax(&mut self, tax: Option<(Decimal, Uint128)>) {
+        self.tax_querier = tax.map(|(rate, cap)| TaxQuerier { rate, cap });
+    }
+
+    // configure the cw721 token owners
+    pub fn with_cw721_owners(&mut self, owners: &[(&str, &[(&str, &str)])]) {
+        self.cw721_querier = Cw721Querier::new(owners);
+    }
+
+    // configure the cw20 token balances
+    pub fn with_token_balances(&mut self, balances: &[(&str, &[(&str, Uint128)])]) {
+        self.cw20_querier = Cw20Querier::new(balances);
+    }
+
+    // configure the cw2981 royalties of collections
+    pub fn with_cw2981_royalties(&mut self, royalties: &[(&str, &str, Decimal)]) {
+        self.cw2981_querier = Cw2981Querier::new(royalties);
     }
 }
@@ -1,4 +1,7 @@
-use cosmwasm_std::{Decimal, Uint128};
+use crate::state::{
+    BidIncrement, EmissionSchedule, FeeTier, SettlementBounty, TaxMode, WashTradingGuard,
+};
+use cosmwasm_std::{Coin, Decimal, Uint128};
 use cw20::Cw20ReceiveMsg;
 use cw721::Cw721ReceiveMsg;
 use schemars::JsonSchema;
@@ -17,26 +20,127 @@ pub struct InstantiateMsg {
     pub sity_partial_rewards: Decimal,
     pub sity_fee_registration: Decimal,
     pub sity_min_opening: Uint128,
+    pub settlement_bounty: Option<SettlementBounty>,
+    #[serde(default)]
+    pub pull_payments: bool,
+    #[serde(default)]
+    pub tax_mode: TaxMode,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum ExecuteMsg {
     /// Place your bid
-    PlaceBid { auction_id: u64 },
+    PlaceBid {
+        auction_id: u64,
+        #[serde(default)]
+        referrer: Option<String>,
+    },
+    /// Escrow your max bid and let the contract outbid others up to it
+    PlaceProxyBid {
+        auction_id: u64,
+        max: Uint128,
+        #[serde(default)]
+        referrer: Option<String>,
+    },
     /// Retire all your bids
     RetractBids { auction_id: u64 },
     /// Owner can withdraw the NFT at the end of the sale
     WithdrawNft { auction_id: u64 },
+    /// Settle a batch of ended auctions, skipping the ones not ready
+    SettleAuctions { auction_ids: Vec<u64> },
+    /// Settle the next ended and unresolved auctions up to the limit
+    SettleNext { limit: Option<u32> },
     /// Instant buy if allowed on the sale
-    InstantBuy { auction_id: u64 },
-    /// Cancel auction
+    InstantBuy {
+        auction_id: u64,
+        #[serde(default)]
+        referrer: Option<String>,
+    },
+    /// Cancel auction, return the NFT, refund bidders and re-mint their burned SITY
     CancelAuction { auction_id: u64 },
+    /// Refund the bidders left on a cancelled auction
+    RefundCancelledBids { auction_id: u64, limit: Option<u32> },
+    /// Rent a listed NFT for the rental duration
+    Rent { rental_id: u64 },
+    /// Return the NFT to the owner and close the rental listing
+    EndRental { rental_id: u64 },
     /// Creator update ROYALTY
     UpdateRoyalty {
         fee: Decimal,
         recipient: Option<String>,
+        #[serde(default)]
+        splits: Vec<RoyaltySplitResponse>,
+    },
+    /// Collection minter or admin update the royalty of a collection
+    UpdateCollectionRoyalty {
+        nft_contract: String,
+        fee: Decimal,
+        recipient: Option<String>,
+        #[serde(default)]
+        splits: Vec<RoyaltySplitResponse>,
+    },
+    /// Admin register the creator and royalty of a collection without known minter
+    UpdateRoyaltyOverride {
+        nft_contract: String,
+        creator: String,
+        fee: Decimal,
+    },
+    /// Admin remove the royalty override of a collection
+    RemoveRoyaltyOverride { nft_contract: String },
+    /// Admin register a verified charity
+    RegisterCharity {
+        name: String,
+        address: String,
+        website: String,
+    },
+    /// Admin update a verified charity, inactive charities can't be used by new auctions
+    UpdateCharity {
+        charity_id: u64,
+        name: String,
+        address: String,
+        website: String,
+        active: bool,
     },
+    /// Admin update the global bid increments schedule
+    UpdateBidIncrements { increments: Vec<BidIncrement> },
+    /// Admin update the fee discounts of sellers holding SITY
+    UpdateFeeTiers { tiers: Vec<FeeTier> },
+    /// Admin update the share of protocol fees paid to stakers and the unbonding cooldown
+    UpdateStakingConfig {
+        fee_share: Decimal,
+        unbonding_period: u64,
+    },
+    /// Admin update the SITY rewards emission schedule, none for unbounded rewards
+    UpdateEmissionSchedule { schedule: Option<EmissionSchedule> },
+    /// Admin update the safeguards suppressing SITY rewards of wash trades, none to disable
+    UpdateWashTradingGuard { guard: Option<WashTradingGuard> },
+    /// Admin update the share of the protocol fee paid to the referrer of the winning bid
+    UpdateReferralShare { share: Decimal },
+    /// Admin update the vesting duration of SITY rewards, 0 to mint them right away
+    UpdateSityVesting { duration: u64 },
+    /// Mint your vested SITY rewards
+    ClaimVested {},
+    /// Start unbonding staked SITY, withdrawable after the unbonding period
+    Unbond { amount: Uint128 },
+    /// Withdraw your SITY once the unbonding period is over
+    WithdrawUnbonded {},
+    /// Claim your share of the protocol fees
+    ClaimStakingRewards {},
+    /// Admin update the bounty paid to keepers settling auctions
+    UpdateSettlementBounty {
+        settlement_bounty: Option<SettlementBounty>,
+    },
+    /// Admin switch payouts between push (sent right away) and pull (claimable) mode
+    UpdatePaymentMode { pull_payments: bool },
+    /// Admin update the tax deducted from native transfers
+    UpdateTaxMode { tax_mode: TaxMode },
+    /// Admin set the cw721 contract minting donation receipts, the marketplace need to be its minter
+    UpdateReceiptContract { receipt_contract: Option<String> },
+    /// Withdraw all your claimable payouts
+    Claim {},
+    /// Retry the delivery of an NFT parked in escrow after a failed transfer
+    ClaimNft { auction_id: u64 },
     /// This accepts a properly-encoded ReceiveMsg from a cw721 contract
     ReceiveNft(Cw721ReceiveMsg),
     /// This accepts a properly-encoded ReceiveMsg from a cw20 contract
@@ -45,6 +149,7 @@ pub enum ExecuteMsg {
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 #[serde(rename_all = "snake_case")]
+#[allow(clippy::large_enum_variant)]
 pub enum ReceiveMsg {
     /// Create an auction and sell your NFT
     CreateAuctionNft {
@@ -55,9 +160,20 @@ pub enum ReceiveMsg {
         instant_buy: Option<Uint128>,
         reserve_price: Option<Uint128>,
         private_sale: bool,
+        gated_collection: Option<String>,
+        min_increment: Option<Uint128>,
+        #[serde(default)]
+        auto_refund: bool,
+        /// Additional charities sharing the donation with `charity`
+        #[serde(default)]
+        charities: Vec<CharityResponse>,
     },
     /// Register private sale
     RegisterPrivateSale { auction_id: u64 },
+    /// List your NFT for rental
+    CreateRental { price: Uint128, duration: u64 },
+    /// Stake SITY to earn a share of the protocol fees
+    Stake {},
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -94,6 +210,66 @@ pub enum QueryMsg {
     Royalty {
         address: String,
     },
+    /// Get the minimum bid accepted by an auction and the funds required for the address
+    NextMinBid {
+        auction_id: u64,
+        address: Option<String>,
+    },
+    /// Get the marketplace fees applied to a seller
+    FeeFor {
+        address: String,
+    },
+    /// Get the staking position of an address
+    Staker {
+        address: String,
+    },
+    /// Get the global staking state
+    StakingState {},
+    /// Get the SITY rewards emission of the current epoch
+    Emission {},
+    /// Get the referral fees earned by an address
+    Referral {
+        address: String,
+    },
+    /// Get the protocol statistics, of a collection if provided
+    Stats {
+        nft_contract: Option<String>,
+    },
+    /// Get the SITY rewards vesting of an address
+    Vesting {
+        address: String,
+    },
+    /// Get rental by id
+    Rental {
+        rental_id: u64,
+    },
+    /// Get the current user of a rented NFT
+    UserOf {
+        nft_contract: String,
+        token_id: String,
+    },
+    /// Get the royalty set for a collection
+    CollectionRoyalty {
+        nft_contract: String,
+    },
+    /// List the royalty overrides registered by the admin
+    RoyaltyOverrides {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// List the verified charities with their donated totals
+    Charities {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    /// Get the payouts claimable by an address
+    Claimable {
+        address: String,
+    },
+    /// Get the NFT parked in escrow after a failed delivery
+    EscrowedNft {
+        auction_id: u64,
+    },
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -103,6 +279,22 @@ pub enum QueryTalisMsg {
     MintingInfo {},
 }
 
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum QueryCw2981Msg {
+    /// Get the royalty owed on a sale of a cw2981 collection
+    RoyaltyInfo {
+        token_id: String,
+        sale_price: Uint128,
+    },
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoyaltiesInfoResponse {
+    pub address: String,
+    pub royalty_amount: Uint128,
+}
+
 // We define a custom struct for each query response
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct AuctionResponse {
@@ -120,6 +312,12 @@ pub struct AuctionResponse {
     pub reserve_price: Option<Uint128>,
     pub private_sale: bool,
     pub resolved: bool,
+    pub gated_collection: Option<String>,
+    pub min_increment: Option<Uint128>,
+    pub auto_refund: bool,
+    pub cancelled: bool,
+    pub verified_charity: bool,
+    pub charities: Vec<CharityResponse>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -127,12 +325,44 @@ pub struct BidResponse {
     pub bid_counter: u64,
     pub total_bid: Uint128,
     pub sity_used: Option<Uint128>,
+    pub proxy_max: Option<Uint128>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CharityResponse {
     pub address: String,
     pub fee_percentage: Decimal,
+    #[serde(default)]
+    pub charity_id: Option<u64>,
+}
+
+/// Extension of the donation receipt NFT minted when a charity auction settle
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DonationReceipt {
+    pub auction_id: u64,
+    pub donations: Vec<DonationResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct DonationResponse {
+    pub charity: String,
+    pub charity_id: Option<u64>,
+    pub amount: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CharityProfileResponse {
+    pub charity_id: u64,
+    pub name: String,
+    pub address: String,
+    pub website: String,
+    pub active: bool,
+    pub donated: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CharitiesResponse {
+    pub charities: Vec<CharityProfileResponse>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -147,12 +377,23 @@ pub struct ConfigResponse {
     pub sity_fee_registration: Decimal,
     pub sity_min_opening: Uint128,
     pub cancellation_fee: Decimal,
+    pub admin: Option<String>,
+    pub bid_increments: Vec<BidIncrement>,
+    pub fee_tiers: Vec<FeeTier>,
+    pub wash_trading_guard: Option<WashTradingGuard>,
+    pub referral_fee_share: Decimal,
+    pub tax_mode: TaxMode,
+    pub settlement_bounty: Option<SettlementBounty>,
+    pub pull_payments: bool,
+    pub receipt_contract: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct StateResponse {
     pub counter_items: u64,
     pub cw20_address: String,
+    pub counter_rentals: u64,
+    pub counter_charities: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -178,7 +419,130 @@ pub struct RoyaltyResponse {
     pub creator: String,
     pub fee: Decimal,
     pub recipient: Option<String>,
+    pub splits: Vec<RoyaltySplitResponse>,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoyaltyOverrideResponse {
+    pub nft_contract: String,
+    pub creator: String,
+    pub fee: Decimal,
+    pub updated_by: String,
+    pub updated_at: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoyaltyOverridesResponse {
+    pub overrides: Vec<RoyaltyOverrideResponse>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoyaltySplitResponse {
+    pub recipient: String,
+    pub share: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct NextMinBidResponse {
+    pub min_bid: Uint128,
+    pub required_funds: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeForResponse {
+    pub sity: Uint128,
+    pub discount: Decimal,
+    pub lota_fee: Decimal,
+    pub lota_fee_low: Decimal,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakerResponse {
+    pub staked: Uint128,
+    pub pending_rewards: Uint128,
+    pub unbonding: Uint128,
+    pub unbonding_release: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StakingStateResponse {
+    pub total_staked: Uint128,
+    pub reward_index: Decimal,
+    pub fee_share: Decimal,
+    pub unbonding_period: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionResponse {
+    pub schedule: Option<EmissionSchedule>,
+    pub epoch: u64,
+    pub epoch_budget: Option<Uint128>,
+    pub emitted: Uint128,
+    pub remaining: Option<Uint128>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct VestingResponse {
+    pub locked: Uint128,
+    pub claimable: Uint128,
+    pub end_time: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralResponse {
+    pub earned: Uint128,
+    pub referred_sales: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct StatsResponse {
+    pub sales: u64,
+    pub cancellations: u64,
+    pub rentals: u64,
+    pub volume: Vec<Coin>,
+    pub lota_fees: Vec<Coin>,
+    pub royalties: Vec<Coin>,
+    pub charity_donated: Vec<Coin>,
+    pub sity_minted: Uint128,
+    pub sity_burned: Uint128,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RentalResponse {
+    pub owner: String,
+    pub nft_contract: String,
+    pub nft_id: String,
+    pub price: Uint128,
+    pub duration: u64,
+    pub renter: Option<String>,
+    pub expires: u64,
+    pub resolved: bool,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct UserOfResponse {
+    pub user: Option<String>,
+    pub expires: u64,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
-pub struct MigrateMsg {}
+pub struct MigrateMsg {
+    pub admin: Option<String>,
+    /// Enable the vesting of SITY rewards
+    #[serde(default)]
+    pub sity_vesting_duration: Option<u64>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ClaimableResponse {
+    pub claimable: Vec<Coin>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EscrowedNftResponse {
+    pub recipient: String,
+    pub nft_contract: String,
+    pub nft_id: String,
+}
@@ -1,7 +1,7 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::{CanonicalAddr, Decimal, Uint128};
+use cosmwasm_std::{CanonicalAddr, Coin, Decimal, Uint128};
 use cw_storage_plus::{Item, Map};
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -15,13 +15,53 @@ pub struct Config {
     pub sity_partial_rewards: Decimal,
     pub sity_fee_registration: Decimal,
     pub sity_min_opening: Uint128,
+    pub settlement_bounty: Option<SettlementBounty>,
+    #[serde(default)]
+    pub pull_payments: bool,
+    #[serde(default)]
+    pub receipt_contract: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub staking_fee_share: Decimal,
+    #[serde(default)]
+    pub unbonding_period: u64,
+    #[serde(default)]
+    pub sity_vesting_duration: u64,
+    #[serde(default)]
+    pub referral_fee_share: Decimal,
+    #[serde(default)]
+    pub tax_mode: TaxMode,
 }
 pub const CONFIG: Item<Config> = Item::new("config");
 
+/*
+   Reward paid to keepers settling auctions they are not part of
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementBounty {
+    FeeShare(Decimal),
+    SityMint(Uint128),
+}
+
+/*
+   Tax deducted from native transfers, Terra tax or none on other chains
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum TaxMode {
+    #[default]
+    Terra,
+    Zero,
+}
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct State {
     pub counter_items: u64,
     pub cw20_address: CanonicalAddr,
+    #[serde(default)]
+    pub counter_rentals: u64,
+    #[serde(default)]
+    pub counter_charities: u64,
 }
 
 pub const STATE: Item<State> = Item::new("state");
@@ -33,12 +73,132 @@ pub struct Cancellation {
 
 pub const CANCELLATION: Item<Cancellation> = Item::new("cancellation");
 
+pub const ADMIN: Item<CanonicalAddr> = Item::new("admin");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Increment {
+    Fixed(Uint128),
+    Percentage(Decimal),
+}
+
+/*
+   Increment applied to bids once the highest bid reach the `from` price
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct BidIncrement {
+    pub from: Uint128,
+    pub increment: Increment,
+}
+
+pub const BID_INCREMENTS: Item<Vec<BidIncrement>> = Item::new("bid_increments");
+
+/*
+   Discount on the marketplace fee once the seller hold `min_sity`
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct FeeTier {
+    pub min_sity: Uint128,
+    pub discount: Decimal,
+}
+
+pub const FEE_TIERS: Item<Vec<FeeTier>> = Item::new("fee_tiers");
+
+/*
+   SITY staking, the stakers share of protocol fees is accrued through the reward index
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct StakingState {
+    pub total_staked: Uint128,
+    pub reward_index: Decimal,
+}
+
+pub const STAKING_STATE: Item<StakingState> = Item::new("staking_state");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct StakerInfo {
+    pub staked: Uint128,
+    pub reward_index: Decimal,
+    pub pending_rewards: Uint128,
+    pub unbonding: Uint128,
+    pub unbonding_release: u64,
+}
+
+pub const STAKERS: Map<&[u8], StakerInfo> = Map::new("stakers");
+
+/*
+   SITY rewards emission, the epoch budget decay at each new epoch
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionSchedule {
+    pub epoch_duration: u64,
+    pub epoch_budget: Uint128,
+    pub decay: Decimal,
+    pub auction_cap: Option<Uint128>,
+}
+
+pub const EMISSION_SCHEDULE: Item<EmissionSchedule> = Item::new("emission_schedule");
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct EmissionState {
+    pub start_time: u64,
+    pub epoch: u64,
+    pub emitted: Uint128,
+}
+
+pub const EMISSION_STATE: Item<EmissionState> = Item::new("emission_state");
+/*
+   No SITY rewards on trades repeating within these windows (seconds)
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct WashTradingGuard {
+    pub pair_window: u64,
+    pub nft_window: u64,
+    pub reward_cooldown: u64,
+}
+
+pub const WASH_TRADING_GUARD: Item<WashTradingGuard> = Item::new("wash_trading_guard");
+pub const PAIR_TRADES: Map<(&[u8], &[u8]), u64> = Map::new("pair_trades");
+pub const NFT_TRADES: Map<(&[u8], &[u8]), u64> = Map::new("nft_trades");
+pub const LAST_REWARDS: Map<&[u8], u64> = Map::new("last_rewards");
+
+/*
+   SITY rewards vesting linearly from `start_time` to `end_time`
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct VestingInfo {
+    pub locked: Uint128,
+    pub claimable: Uint128,
+    pub start_time: u64,
+    pub end_time: u64,
+}
+
+pub const VESTINGS: Map<&[u8], VestingInfo> = Map::new("vestings");
+
+pub const AUCTION_EMISSIONS: Map<&[u8], Uint128> = Map::new("auction_emissions");
+
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct CharityInfo {
     pub address: CanonicalAddr,
     pub fee_percentage: Decimal,
+    #[serde(default)]
+    pub charity_id: Option<u64>,
 }
 
+/*
+   Charity verified by the admin, auctions referencing it are flagged verified
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct CharityProfile {
+    pub name: String,
+    pub address: CanonicalAddr,
+    pub website: String,
+    pub active: bool,
+    pub donated: Uint128,
+}
+
+pub const CHARITIES: Map<&[u8], CharityProfile> = Map::new("charities");
+
 /*
    TODO: Should we ask for a collateral for selling ? in order to limit spam
 */
@@ -58,9 +218,37 @@ pub struct ItemInfo {
     pub reserve_price: Option<Uint128>,
     pub private_sale: bool,
     pub resolved: bool,
+    pub gated_collection: Option<CanonicalAddr>,
+    pub min_increment: Option<Uint128>,
+    #[serde(default)]
+    pub auto_refund: bool,
+    #[serde(default)]
+    pub cancelled: bool,
+    #[serde(default)]
+    pub charities: Vec<CharityInfo>,
 }
 
 pub const ITEMS: Map<&[u8], ItemInfo> = Map::new("items");
+// Unresolved auction ids by end time, to find the auctions ready to settle
+pub const OPEN_AUCTIONS: Map<(&[u8], &[u8]), u64> = Map::new("open_auctions");
+
+/*
+   Rental listing, the renter is the user of the NFT until expires
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RentalInfo {
+    pub owner: CanonicalAddr,
+    pub nft_contract: CanonicalAddr,
+    pub nft_id: String,
+    pub price: Uint128,
+    pub duration: u64,
+    pub renter: Option<CanonicalAddr>,
+    pub expires: u64,
+    pub resolved: bool,
+}
+
+pub const RENTALS: Map<&[u8], RentalInfo> = Map::new("rentals");
+pub const RENTALS_BY_NFT: Map<(&[u8], &[u8]), u64> = Map::new("rentals_by_nft");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct BidInfo {
@@ -68,9 +256,54 @@ pub struct BidInfo {
     pub total_bid: Uint128,
     pub sity_used: Option<Uint128>,
     pub resolved: bool,
+    pub proxy_max: Option<Uint128>,
 }
 
 pub const BIDS: Map<(&[u8], &[u8]), BidInfo> = Map::new("bids");
+// Referrer of the last bid of a bidder on an auction
+pub const BID_REFERRERS: Map<(&[u8], &[u8]), CanonicalAddr> = Map::new("bid_referrers");
+
+/*
+   Referral fees earned by the referrer
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct ReferralInfo {
+    pub earned: Uint128,
+    pub referred_sales: u64,
+}
+
+pub const REFERRALS: Map<&[u8], ReferralInfo> = Map::new("referrals");
+
+/*
+   Cumulative protocol statistics, overall and per collection
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct Stats {
+    pub sales: u64,
+    pub cancellations: u64,
+    #[serde(default)]
+    pub rentals: u64,
+    pub volume: Vec<Coin>,
+    pub lota_fees: Vec<Coin>,
+    pub royalties: Vec<Coin>,
+    pub charity_donated: Vec<Coin>,
+    // SITY rewarded, vested rewards included
+    pub sity_minted: Uint128,
+    pub sity_burned: Uint128,
+}
+
+pub const STATS: Item<Stats> = Item::new("stats");
+pub const COLLECTION_STATS: Map<&[u8], Stats> = Map::new("collection_stats");
+
+/*
+   NFT whose delivery failed at settlement waiting for the recipient to claim it
+*/
+pub const NFT_ESCROW: Map<&[u8], CanonicalAddr> = Map::new("nft_escrow");
+
+/*
+   Payouts credited in pull mode waiting to be claimed (address, denom)
+*/
+pub const CLAIMABLE: Map<(&[u8], &[u8]), Uint128> = Map::new("claimable");
 
 /*
    History bidder info
@@ -107,8 +340,37 @@ pub struct RoyaltyInfo {
     pub creator: CanonicalAddr,
     pub fee: Decimal,
     pub recipient: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub splits: Vec<RoyaltySplit>,
+    #[serde(default)]
+    pub updated_by: Option<CanonicalAddr>,
+    #[serde(default)]
+    pub updated_at: Option<u64>,
+}
+
+/*
+   Share of the sale price paid to a collaborator, shares sum to the royalty fee
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoyaltySplit {
+    pub recipient: CanonicalAddr,
+    pub share: Decimal,
 }
 pub const ROYALTY: Map<&[u8], RoyaltyInfo> = Map::new("royalty");
+// Royalty by nft contract, set by the collection minter or the admin
+pub const COLLECTION_ROYALTY: Map<&[u8], RoyaltyInfo> = Map::new("collection_royalty");
+
+/*
+   Creator and royalty registered by the admin for collections without known minter
+*/
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RoyaltyOverride {
+    pub creator: CanonicalAddr,
+    pub fee: Decimal,
+    pub updated_by: CanonicalAddr,
+    pub updated_at: u64,
+}
+pub const ROYALTY_OVERRIDES: Map<&[u8], RoyaltyOverride> = Map::new("royalty_overrides");
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
 pub struct TalisInfo {
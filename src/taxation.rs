@@ -2,25 +2,63 @@ use cosmwasm_std::{Coin, Decimal, QuerierWrapper, StdResult, Uint128};
 
 use terra_cosmwasm::TerraQuerier;
 
+use crate::state::TaxMode;
+
 static DECIMAL_FRACTION: Uint128 = Uint128::new(1_000_000_000_000_000_000u128);
 
-pub fn compute_tax(querier: &QuerierWrapper, coin: &Coin) -> StdResult<Uint128> {
-    let terra_querier = TerraQuerier::new(querier);
-    let tax_rate: Decimal = (terra_querier.query_tax_rate()?).rate;
-    let tax_cap: Uint128 = (terra_querier.query_tax_cap(coin.denom.to_string())?).cap;
-    Ok(std::cmp::min(
-        (coin.amount.checked_sub(coin.amount.multiply_ratio(
-            DECIMAL_FRACTION,
-            DECIMAL_FRACTION * tax_rate + DECIMAL_FRACTION,
-        )))?,
-        tax_cap,
-    ))
+/*
+   Tax applied by the chain on native transfers sent by the contract
+*/
+pub trait Taxation {
+    fn compute_tax(&self, querier: &QuerierWrapper, coin: &Coin) -> StdResult<Uint128>;
+
+    fn deduct_tax(&self, querier: &QuerierWrapper, coin: Coin) -> StdResult<Coin> {
+        let tax_amount = self.compute_tax(querier, &coin)?;
+        Ok(Coin {
+            denom: coin.denom,
+            amount: (coin.amount.checked_sub(tax_amount))?,
+        })
+    }
+}
+
+/*
+   Terra tax, queried from the chain tax rate and cap
+*/
+pub struct TerraTaxation;
+
+impl Taxation for TerraTaxation {
+    fn compute_tax(&self, querier: &QuerierWrapper, coin: &Coin) -> StdResult<Uint128> {
+        let terra_querier = TerraQuerier::new(querier);
+        let tax_rate: Decimal = (terra_querier.query_tax_rate()?).rate;
+        let tax_cap: Uint128 = (terra_querier.query_tax_cap(coin.denom.to_string())?).cap;
+        Ok(std::cmp::min(
+            (coin.amount.checked_sub(coin.amount.multiply_ratio(
+                DECIMAL_FRACTION,
+                DECIMAL_FRACTION * tax_rate + DECIMAL_FRACTION,
+            )))?,
+            tax_cap,
+        ))
+    }
+}
+
+/*
+   No tax, for chains without transfer tax
+*/
+pub struct ZeroTaxation;
+
+impl Taxation for ZeroTaxation {
+    fn compute_tax(&self, _querier: &QuerierWrapper, _coin: &Coin) -> StdResult<Uint128> {
+        Ok(Uint128::zero())
+    }
+}
+
+pub fn taxation(tax_mode: &TaxMode) -> &'static dyn Taxation {
+    match tax_mode {
+        TaxMode::Terra => &TerraTaxation,
+        TaxMode::Zero => &ZeroTaxation,
+    }
 }
 
-pub fn deduct_tax(querier: &QuerierWrapper, coin: Coin) -> StdResult<Coin> {
-    let tax_amount = compute_tax(querier, &coin)?;
-    Ok(Coin {
-        denom: coin.denom,
-        amount: (coin.amount.checked_sub(tax_amount))?,
-    })
+pub fn deduct_tax(querier: &QuerierWrapper, tax_mode: &TaxMode, coin: Coin) -> StdResult<Coin> {
+    taxation(tax_mode).deduct_tax(querier, coin)
 }